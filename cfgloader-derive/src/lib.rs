@@ -0,0 +1,131 @@
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_macro_input, DeriveInput, Lit, Meta, NestedMeta};
+
+/// Binds a struct to a config namespace, name, and on-disk format, generating
+/// `load`, `load_or_default`, and `save` methods so call sites don't repeat the
+/// same string literals at every use site.
+///
+/// ```ignore
+/// #[derive(Config, Serialize, Deserialize, Default)]
+/// #[config(namespace = "myapp", name = "config", format = "toml")]
+/// struct Settings {
+///     port: u16,
+/// }
+///
+/// let settings = Settings::load_or_default()?;
+/// settings.save()?;
+/// ```
+///
+/// `format` defaults to `"toml"` and is only used to pick an extension the first
+/// time `save` creates the file; an existing file's on-disk extension always wins.
+#[proc_macro_derive(Config, attributes(config))]
+pub fn derive_config(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    match expand(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+fn expand(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let ident = &input.ident;
+    let attrs = ConfigAttrs::from_derive_input(input)?;
+
+    let namespace = attrs.namespace;
+    let name = attrs.name;
+    let format = attrs.format;
+
+    Ok(quote! {
+        impl #ident {
+            pub fn load() -> ::std::result::Result<Self, ::cfgloader::Error> {
+                ::cfgloader::load(#namespace, #name, ::cfgloader::EnvOverride::None)
+            }
+
+            pub fn load_or_default() -> ::std::result::Result<Self, ::cfgloader::Error>
+            where
+                Self: ::std::default::Default,
+            {
+                ::cfgloader::load_or_default(#namespace, #name, Self::default())
+            }
+
+            pub fn save(&self) -> ::std::result::Result<(), ::cfgloader::Error> {
+                ::cfgloader::save_with_format(#namespace, #name, self, #format)
+            }
+        }
+    })
+}
+
+struct ConfigAttrs {
+    namespace: String,
+    name: String,
+    format: String,
+}
+
+impl ConfigAttrs {
+    fn from_derive_input(input: &DeriveInput) -> syn::Result<Self> {
+        let mut namespace = None;
+        let mut name = None;
+        let mut format = "toml".to_string();
+
+        for attr in &input.attrs {
+            if !attr.path.is_ident("config") {
+                continue;
+            }
+
+            let list = match attr.parse_meta()? {
+                Meta::List(list) => list,
+                other => {
+                    return Err(syn::Error::new_spanned(
+                        other,
+                        "#[config(...)] must be a list of name = \"value\" pairs",
+                    ))
+                }
+            };
+
+            for nested in list.nested {
+                let pair = match nested {
+                    NestedMeta::Meta(Meta::NameValue(pair)) => pair,
+                    other => {
+                        return Err(syn::Error::new_spanned(
+                            other,
+                            "#[config(...)] entries must be name = \"value\" pairs",
+                        ))
+                    }
+                };
+                let value = match &pair.lit {
+                    Lit::Str(s) => s.value(),
+                    other => {
+                        return Err(syn::Error::new_spanned(
+                            other,
+                            "#[config] attribute values must be strings",
+                        ))
+                    }
+                };
+
+                if pair.path.is_ident("namespace") {
+                    namespace = Some(value);
+                } else if pair.path.is_ident("name") {
+                    name = Some(value);
+                } else if pair.path.is_ident("format") {
+                    format = value;
+                }
+            }
+        }
+
+        Ok(Self {
+            namespace: namespace.ok_or_else(|| {
+                syn::Error::new_spanned(
+                    &input.ident,
+                    "#[config(namespace = \"...\")] is required",
+                )
+            })?,
+            name: name.ok_or_else(|| {
+                syn::Error::new_spanned(&input.ident, "#[config(name = \"...\")] is required")
+            })?,
+            format,
+        })
+    }
+}
@@ -0,0 +1,99 @@
+use std::env;
+
+use serde_json::{Map, Value};
+
+use crate::{Error, Result};
+
+// Overlays environment variables starting with `PREFIX_` (case-insensitive) onto
+// `value`, splitting the rest of the name on `__` into a path of nested object keys.
+pub(crate) fn apply(value: &mut Value, prefix: &str) -> Result<()> {
+    let prefix = format!("{}_", prefix.to_uppercase());
+
+    for (key, raw) in env::vars() {
+        let upper = key.to_uppercase();
+        if !upper.starts_with(&prefix) {
+            continue;
+        }
+
+        let path: Vec<&str> = upper[prefix.len()..].split("__").collect();
+        set_path(value, &path, &raw)?;
+    }
+
+    Ok(())
+}
+
+// Walks (creating as needed) the object path described by `path`, erroring instead
+// of panicking if a segment expects an object but finds a scalar already there.
+fn set_path(root: &mut Value, path: &[&str], raw: &str) -> Result<()> {
+    let (leaf, branches) = path.split_last().expect("env var path is never empty");
+
+    let mut current = root;
+    for segment in branches {
+        let map = current
+            .as_object_mut()
+            .ok_or_else(|| Error::EnvOverrideConflict {
+                path: path.join("__"),
+            })?;
+        current = map
+            .entry(segment.to_lowercase())
+            .or_insert_with(|| Value::Object(Map::new()));
+    }
+
+    let map = current
+        .as_object_mut()
+        .ok_or_else(|| Error::EnvOverrideConflict {
+            path: path.join("__"),
+        })?;
+    map.insert(leaf.to_lowercase(), parse_scalar(raw));
+
+    Ok(())
+}
+
+// Parses into the most specific scalar that fits: bool, then integer, then float,
+// falling back to string. An empty value is kept as an empty string.
+fn parse_scalar(raw: &str) -> Value {
+    if raw.is_empty() {
+        return Value::String(String::new());
+    }
+
+    if let Ok(b) = raw.parse::<bool>() {
+        Value::Bool(b)
+    } else if let Ok(i) = raw.parse::<i64>() {
+        Value::Number(i.into())
+    } else if let Ok(f) = raw.parse::<f64>() {
+        serde_json::Number::from_f64(f)
+            .map(Value::Number)
+            .unwrap_or_else(|| Value::String(raw.to_string()))
+    } else {
+        Value::String(raw.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_path_creates_nested_objects() {
+        let mut value = Value::Object(Map::new());
+        set_path(&mut value, &["FOO", "BAR"], "1").unwrap();
+        assert_eq!(value["foo"]["bar"], Value::Number(1.into()));
+    }
+
+    #[test]
+    fn set_path_errors_instead_of_panicking_on_leaf_branch_conflict() {
+        let mut value = Value::Object(Map::new());
+        set_path(&mut value, &["FOO"], "1").unwrap();
+
+        let err = set_path(&mut value, &["FOO", "BAR"], "2").unwrap_err();
+        assert!(matches!(err, Error::EnvOverrideConflict { .. }));
+    }
+
+    #[test]
+    fn parse_scalar_prefers_the_most_specific_type() {
+        assert_eq!(parse_scalar("true"), Value::Bool(true));
+        assert_eq!(parse_scalar("42"), Value::Number(42.into()));
+        assert_eq!(parse_scalar(""), Value::String(String::new()));
+        assert_eq!(parse_scalar("hello"), Value::String("hello".to_string()));
+    }
+}
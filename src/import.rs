@@ -0,0 +1,165 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use serde_json::{Map, Value};
+
+use polyglot::Format;
+
+use crate::merge::deep_merge;
+use crate::{read_value, Error, Result};
+
+// How many levels of `imports` an importing file may chain before we give up and
+// assume something is wrong.
+const IMPORT_RECURSION_LIMIT: usize = 5;
+
+// Reads `file` and resolves its top-level `imports` array (if any), merging each
+// imported file in declaration order and finally the importing file itself on top.
+// The returned value has the `imports` key stripped.
+pub(crate) fn resolve(file: &Path, format: Format) -> Result<Value> {
+    let mut chain = HashSet::new();
+    resolve_inner(file, format, &mut chain, 0)
+}
+
+fn resolve_inner(
+    file: &Path,
+    format: Format,
+    chain: &mut HashSet<PathBuf>,
+    depth: usize,
+) -> Result<Value> {
+    if depth > IMPORT_RECURSION_LIMIT {
+        return Err(Error::ImportDepthExceeded);
+    }
+
+    let canonical = file.canonicalize().unwrap_or_else(|_| file.to_path_buf());
+    if !chain.insert(canonical.clone()) {
+        return Err(Error::ImportCycle {
+            path: file.to_path_buf(),
+        });
+    }
+
+    let mut value = read_value(file, format)?;
+    let dir = file.parent().unwrap_or_else(|| Path::new("."));
+
+    let imports = match &mut value {
+        Value::Object(map) => map.remove("imports"),
+        _ => None,
+    };
+    let imports = match imports {
+        Some(Value::Array(entries)) => entries,
+        Some(_) => {
+            return Err(Error::InvalidImports {
+                path: file.to_path_buf(),
+            })
+        }
+        None => Vec::new(),
+    };
+
+    let mut merged = Value::Object(Map::new());
+    for entry in imports {
+        let raw_path = entry.as_str().ok_or_else(|| Error::InvalidImports {
+            path: file.to_path_buf(),
+        })?;
+
+        let import_path = resolve_import_path(dir, raw_path);
+        let import_format = format_for_path(&import_path)?;
+        let imported = resolve_inner(&import_path, import_format, chain, depth + 1)?;
+        deep_merge(&mut merged, imported);
+    }
+
+    chain.remove(&canonical);
+
+    deep_merge(&mut merged, value);
+    Ok(merged)
+}
+
+// Resolves an `imports` entry relative to the importing file's directory, with `~`
+// expanded to the user's home directory.
+fn resolve_import_path(base_dir: &Path, raw: &str) -> PathBuf {
+    let expanded = match raw.strip_prefix("~/") {
+        Some(rest) => dirs::home_dir()
+            .map(|home| home.join(rest))
+            .unwrap_or_else(|| PathBuf::from(raw)),
+        None => PathBuf::from(raw),
+    };
+
+    if expanded.is_absolute() {
+        expanded
+    } else {
+        base_dir.join(expanded)
+    }
+}
+
+fn format_for_path(path: &Path) -> Result<Format> {
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("toml") => Ok(Format::TOML),
+        Some("json") => Ok(Format::JSON),
+        Some("yml") | Some("yaml") => Ok(Format::YAML),
+        _ => Err(Error::UnsupportedImportFormat {
+            path: path.to_path_buf(),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "cfgloader-import-test-{}-{}",
+            name,
+            std::process::id()
+        ));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn resolve_detects_import_cycles() {
+        let dir = temp_dir("cycle");
+        fs::write(dir.join("a.toml"), "imports = [\"b.toml\"]\n").unwrap();
+        fs::write(dir.join("b.toml"), "imports = [\"a.toml\"]\n").unwrap();
+
+        let err = resolve(&dir.join("a.toml"), Format::TOML).unwrap_err();
+        assert!(matches!(err, Error::ImportCycle { .. }));
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn resolve_allows_diamond_imports() {
+        let dir = temp_dir("diamond");
+        fs::write(dir.join("base.toml"), "value = 1\n").unwrap();
+        fs::write(dir.join("a.toml"), "imports = [\"base.toml\"]\n").unwrap();
+        fs::write(dir.join("b.toml"), "imports = [\"base.toml\"]\n").unwrap();
+        fs::write(
+            dir.join("top.toml"),
+            "imports = [\"a.toml\", \"b.toml\"]\n",
+        )
+        .unwrap();
+
+        let value = resolve(&dir.join("top.toml"), Format::TOML).unwrap();
+        assert_eq!(value["value"], serde_json::json!(1));
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn resolve_errors_past_the_recursion_limit() {
+        let dir = temp_dir("deep");
+        for i in 0..=IMPORT_RECURSION_LIMIT {
+            fs::write(
+                dir.join(format!("{}.toml", i)),
+                format!("imports = [\"{}.toml\"]\n", i + 1),
+            )
+            .unwrap();
+        }
+
+        let err = resolve(&dir.join("0.toml"), Format::TOML).unwrap_err();
+        assert!(matches!(err, Error::ImportDepthExceeded));
+
+        fs::remove_dir_all(&dir).ok();
+    }
+}
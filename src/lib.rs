@@ -8,6 +8,10 @@ use snafu::{ResultExt, Snafu};
 
 use polyglot::Format;
 
+mod env;
+mod import;
+mod merge;
+
 #[derive(Debug, Snafu)]
 pub enum Error {
     #[snafu(display("Unknown config directory"))]
@@ -17,36 +21,131 @@ pub enum Error {
         source: io::Error,
     },
 
-    FailedToCreateDefaultConfigFile {
+    FailedToCreateConfigFile {
         source: io::Error,
     },
 
-    FailedToSerializeDefaultConfig {
+    FailedToSerializeConfig {
         source: polyglot::Error,
     },
 
     #[snafu(display("Failed to find config file"))]
     FailedToFindConfigFile,
 
+    #[snafu(display("Failed to open config file '{}'", path.display()))]
     FailedToOpenConfigFile {
+        path: PathBuf,
         source: io::Error,
     },
 
+    #[snafu(display(
+        "Failed to parse config file '{}': {}\n---\n{}\n---",
+        path.display(),
+        source,
+        snippet
+    ))]
     FailedToDeserializeConfigFile {
+        path: PathBuf,
+        snippet: String,
         source: polyglot::Error,
     },
+
+    #[snafu(display("Environment variable targets path '{}' through a non-object value", path))]
+    EnvOverrideConflict {
+        path: String,
+    },
+
+    #[snafu(display(
+        "Failed to deserialize config file '{}' into the target type: {}\n---\n{}\n---",
+        path.display(),
+        source,
+        snippet
+    ))]
+    FailedToBuildConfig {
+        path: PathBuf,
+        snippet: String,
+        source: serde_json::Error,
+    },
+
+    #[snafu(display("Import cycle detected at '{}'", path.display()))]
+    ImportCycle {
+        path: PathBuf,
+    },
+
+    #[snafu(display("Exceeded import recursion limit"))]
+    ImportDepthExceeded,
+
+    #[snafu(display(
+        "Import '{}' has an unrecognized or missing file extension",
+        path.display()
+    ))]
+    UnsupportedImportFormat {
+        path: PathBuf,
+    },
+
+    #[snafu(display("'imports' in '{}' must be an array of path strings", path.display()))]
+    InvalidImports {
+        path: PathBuf,
+    },
+
+    FailedToGetCurrentDir {
+        source: io::Error,
+    },
+
+    #[snafu(display("No '{}' config file found in the current directory or any parent", name))]
+    FailedToDiscoverConfigFile {
+        name: String,
+    },
+
+    FailedToBuildDefaultValue {
+        source: serde_json::Error,
+    },
 }
 
 type Result<T> = std::result::Result<T, Error>;
 
+/// Reads `file` into a generic JSON value without resolving `imports`, so it can be
+/// merged and transformed before finally landing on a concrete type.
+///
+/// The file is read to a string up front (rather than streamed straight into the
+/// parser) so that a parse failure can point back at the offending file and show
+/// the text that didn't parse, instead of just the bare underlying error.
+fn read_value(file: &Path, format: Format) -> Result<serde_json::Value> {
+    let contents = fs::read_to_string(file).context(FailedToOpenConfigFile {
+        path: file.to_path_buf(),
+    })?;
+
+    polyglot::from_reader(contents.as_bytes(), format).map_err(|source| {
+        Error::FailedToDeserializeConfigFile {
+            path: file.to_path_buf(),
+            snippet: contents,
+            source,
+        }
+    })
+}
+
 fn deser<T: DeserializeOwned>(file: &Path, format: Format) -> Result<T> {
-    let f = fs::File::open(file).context(FailedToOpenConfigFile)?;
-    polyglot::from_reader(f, format).context(FailedToDeserializeConfigFile)
+    let value = import::resolve(file, format)?;
+    finalize(value, file)
+}
+
+/// Deserializes a fully-resolved (imports merged, env applied, etc.) value into
+/// `T`, attributing any failure back to the file it originated from.
+fn finalize<T: DeserializeOwned>(value: serde_json::Value, file: &Path) -> Result<T> {
+    T::deserialize(&value).map_err(|source| Error::FailedToBuildConfig {
+        path: file.to_path_buf(),
+        snippet: serde_json::to_string_pretty(&value).unwrap_or_default(),
+        source,
+    })
 }
 
 fn find_config_file(namespace: &str, name: &str) -> Result<Option<(PathBuf, Format)>> {
     let config_dir = dirs::config_dir().ok_or(Error::UnknownConfigDirectory)?;
-    let file_path = config_dir.join(namespace).join(name);
+    Ok(find_config_file_in(&config_dir.join(namespace), name))
+}
+
+fn find_config_file_in(dir: &Path, name: &str) -> Option<(PathBuf, Format)> {
+    let file_path = dir.join(name);
 
     let (ext, format) = if file_path.with_extension("toml").exists() {
         ("toml", Format::TOML)
@@ -55,16 +154,61 @@ fn find_config_file(namespace: &str, name: &str) -> Result<Option<(PathBuf, Form
     } else if file_path.with_extension("yml").exists() {
         ("yml", Format::YAML)
     } else {
-        return Ok(None);
+        return None;
     };
 
-    Ok(Some((file_path.with_extension(ext), format)))
+    Some((file_path.with_extension(ext), format))
 }
 
-pub fn load<T: DeserializeOwned>(namespace: &str, name: &str) -> Result<T> {
+// Whether `load` should overlay environment variables onto the file config, and
+// under what prefix. `load_with_env` is a thin convenience wrapper around
+// `load(namespace, name, EnvOverride::Prefix(prefix))`.
+pub enum EnvOverride<'a> {
+    None,
+    Prefix(&'a str),
+}
+
+pub fn load<T: DeserializeOwned>(
+    namespace: &str,
+    name: &str,
+    env_override: EnvOverride,
+) -> Result<T> {
     let (file_path, format) =
         find_config_file(namespace, name)?.ok_or(Error::FailedToFindConfigFile)?;
-    deser(&file_path, format)
+
+    let mut value = import::resolve(&file_path, format)?;
+    if let EnvOverride::Prefix(prefix) = env_override {
+        env::apply(&mut value, prefix)?;
+    }
+
+    finalize(value, &file_path)
+}
+
+pub fn load_with_env<T: DeserializeOwned>(namespace: &str, name: &str, prefix: &str) -> Result<T> {
+    load(namespace, name, EnvOverride::Prefix(prefix))
+}
+
+// Walks up from the current working directory, looking for `name.{toml,json,yml}`
+// at every level, and loads the first one found. Returns the resolved path
+// alongside the value so callers can report where the config came from.
+pub fn discover<T: DeserializeOwned>(name: &str) -> Result<(T, PathBuf)> {
+    let mut dir = std::env::current_dir().context(FailedToGetCurrentDir)?;
+
+    loop {
+        if let Some((file_path, format)) = find_config_file_in(&dir, name) {
+            let value = deser(&file_path, format)?;
+            return Ok((value, file_path));
+        }
+
+        match dir.parent() {
+            Some(parent) => dir = parent.to_path_buf(),
+            None => break,
+        }
+    }
+
+    Err(Error::FailedToDiscoverConfigFile {
+        name: name.to_string(),
+    })
 }
 
 pub fn load_or_default<T: DeserializeOwned + Serialize>(
@@ -81,9 +225,89 @@ pub fn load_or_default<T: DeserializeOwned + Serialize>(
         fs::create_dir_all(&namespace_dir).context(FailedToCreateConfigDir)?;
         let file_path = namespace_dir.join(name).with_extension("toml");
 
-        let f = fs::File::create(&file_path).context(FailedToCreateDefaultConfigFile)?;
-        polyglot::to_writer(f, &default, Format::TOML).context(FailedToSerializeDefaultConfig)?;
+        let f = fs::File::create(&file_path).context(FailedToCreateConfigFile)?;
+        polyglot::to_writer(f, &default, Format::TOML).context(FailedToSerializeConfig)?;
 
         Ok(default)
     }
 }
+
+/// Serializes `value` back to `namespace/name`, reusing the existing file's
+/// detected format if one is present, or TOML otherwise.
+///
+/// Pairs with [`load`]/[`load_or_default`]/[`load_merged`] for settings that
+/// mutate at runtime and need to be persisted explicitly, rather than only ever
+/// implicitly (and hardcoded to TOML) inside [`load_or_default`].
+pub fn save<T: Serialize>(namespace: &str, name: &str, value: &T) -> Result<()> {
+    save_with_format(namespace, name, value, "toml")
+}
+
+/// Serializes `value` to `namespace/name`, reusing the format of the file already
+/// on disk if one exists, or `default_format` (`"toml"`, `"json"`, `"yml"`/`"yaml"`)
+/// when creating it for the first time.
+///
+/// Not part of the stable public API: this backs the `save` method generated by
+/// `#[derive(Config)]` in `cfgloader-derive`, which is where the per-struct default
+/// format comes from.
+#[doc(hidden)]
+pub fn save_with_format<T: Serialize>(
+    namespace: &str,
+    name: &str,
+    value: &T,
+    default_format: &str,
+) -> Result<()> {
+    let config_dir = dirs::config_dir().ok_or(Error::UnknownConfigDirectory)?;
+    let namespace_dir = config_dir.join(namespace);
+    fs::create_dir_all(&namespace_dir).context(FailedToCreateConfigDir)?;
+
+    let (file_path, format) = match find_config_file(namespace, name)? {
+        Some(found) => found,
+        None => {
+            let format = format_from_str(default_format);
+            let ext = extension_for_format(&format);
+            (namespace_dir.join(name).with_extension(ext), format)
+        }
+    };
+
+    let f = fs::File::create(&file_path).context(FailedToCreateConfigFile)?;
+    polyglot::to_writer(f, value, format).context(FailedToSerializeConfig)
+}
+
+fn format_from_str(format: &str) -> Format {
+    match format {
+        "json" => Format::JSON,
+        "yml" | "yaml" => Format::YAML,
+        _ => Format::TOML,
+    }
+}
+
+fn extension_for_format(format: &Format) -> &'static str {
+    match format {
+        Format::JSON => "json",
+        Format::YAML => "yml",
+        Format::TOML => "toml",
+    }
+}
+
+/// Like [`load_or_default`], but when a config file does exist it no longer needs
+/// to specify every field: any key missing from the file is filled in from
+/// `default`, so users can override just the handful of settings they care about.
+///
+/// Unlike [`load_or_default`], when no config file exists `default` is returned
+/// as is and nothing is written to disk.
+pub fn load_merged<T: DeserializeOwned + Serialize>(
+    namespace: &str,
+    name: &str,
+    default: T,
+) -> Result<T> {
+    let (file, format) = match find_config_file(namespace, name)? {
+        Some(found) => found,
+        None => return Ok(default),
+    };
+
+    let mut value = serde_json::to_value(&default).context(FailedToBuildDefaultValue)?;
+    let file_value = import::resolve(&file, format)?;
+    merge::deep_merge(&mut value, file_value);
+
+    finalize(value, &file)
+}
@@ -0,0 +1,63 @@
+use serde_json::Value;
+
+/// Recursively merges `overlay` into `base`, with `overlay` winning wherever a key
+/// is present in both. Objects are merged key-by-key; any other combination
+/// (scalars, arrays, or a type mismatch between the two sides) is replaced
+/// wholesale by `overlay`.
+pub(crate) fn deep_merge(base: &mut Value, overlay: Value) {
+    match overlay {
+        Value::Object(overlay_map) => {
+            if let Value::Object(base_map) = base {
+                for (key, value) in overlay_map {
+                    match base_map.get_mut(&key) {
+                        Some(existing) => deep_merge(existing, value),
+                        None => {
+                            base_map.insert(key, value);
+                        }
+                    }
+                }
+            } else {
+                *base = Value::Object(overlay_map);
+            }
+        }
+        other => *base = other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deep_merge_overlays_nested_objects_key_by_key() {
+        let mut base = serde_json::json!({"a": 1, "nested": {"x": 1, "y": 2}});
+        let overlay = serde_json::json!({"nested": {"y": 20, "z": 3}});
+
+        deep_merge(&mut base, overlay);
+
+        assert_eq!(
+            base,
+            serde_json::json!({"a": 1, "nested": {"x": 1, "y": 20, "z": 3}})
+        );
+    }
+
+    #[test]
+    fn deep_merge_replaces_scalars_and_arrays_wholesale() {
+        let mut base = serde_json::json!({"a": [1, 2, 3], "b": "base"});
+        let overlay = serde_json::json!({"a": [9], "b": "overlay"});
+
+        deep_merge(&mut base, overlay);
+
+        assert_eq!(base, serde_json::json!({"a": [9], "b": "overlay"}));
+    }
+
+    #[test]
+    fn deep_merge_replaces_an_object_with_a_scalar_overlay() {
+        let mut base = serde_json::json!({"a": {"x": 1}});
+        let overlay = serde_json::json!({"a": "scalar"});
+
+        deep_merge(&mut base, overlay);
+
+        assert_eq!(base, serde_json::json!({"a": "scalar"}));
+    }
+}